@@ -0,0 +1,66 @@
+//! Types shared across every `wair` backend. Platform modules parameterise
+//! [`Event`] with their own window- and device-identifier types and translate
+//! native events into it, so downstream consumers see one event vocabulary
+//! regardless of the underlying windowing or input system.
+
+/// Marker for a backend's window-identifier type.
+pub trait WindowID: Clone + PartialEq {}
+
+/// Marker for a backend's device-identifier type.
+pub trait DeviceID: Clone + PartialEq {}
+
+/// An axis on a device: relative (e.g. a mouse) or absolute (e.g. a stick).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AxisID(pub u32);
+
+/// A button or key on a device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ButtonID(pub u32);
+
+/// A single input or windowing event, generic over the backend's window and
+/// device identifier types.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<Window, Device> {
+    /// A new device became available.
+    DeviceAdded { device: Device },
+    /// A device was removed.
+    DeviceRemoved { device: Device },
+    /// Access to a device was revoked by the seat manager (e.g. a VT switch);
+    /// no further events arrive for it until a matching `DeviceResumed`.
+    DevicePaused { device: Device },
+    /// Access to a previously paused device was restored.
+    DeviceResumed { device: Device },
+    /// A device's state may have jumped (e.g. an evdev `SYN_DROPPED`); stateful
+    /// consumers should resynchronise before applying the events that follow.
+    DeviceSync { device: Device },
+    /// A button or key went down.
+    RawButtonPress { device: Device, button: ButtonID },
+    /// A button or key came up.
+    RawButtonRelease { device: Device, button: ButtonID },
+    /// A relative or absolute axis moved, in the axis' native units.
+    RawMotion { device: Device, axis: AxisID, value: f64 },
+    /// An absolute axis moved, carrying both the raw reading and a `normalized`
+    /// value scaled against the axis' calibration (`[-1.0, 1.0]`, or
+    /// `[0.0, 1.0]` for single-ended axes).
+    RawAbsMotion { device: Device, axis: AxisID, value: f64, normalized: f64 },
+    /// An event scoped to a particular window.
+    Window { window: Window, event: WindowEvent },
+}
+
+/// An event delivered to a specific window.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WindowEvent {
+    /// The window was asked to close.
+    Quit,
+    /// Input directed at the window.
+    Input { event: InputEvent },
+}
+
+/// Window-directed input, already resolved to logical keys/buttons.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputEvent {
+    /// A key was pressed, identified by its keysym.
+    KeyPress { keysym: u32 },
+    /// A key was released, identified by its keysym.
+    KeyRelease { keysym: u32 },
+}
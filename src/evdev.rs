@@ -1,10 +1,13 @@
 use std::error::Error;
 use std::ffi::{CStr, CString, OsStr};
-use std::os::unix::io::AsRawFd;
-use std::collections::{HashMap, VecDeque};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::os::unix::ffi::OsStrExt;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::hash::{Hash, Hasher};
 use std::cell::RefCell;
-use std::io;
+use std::io::{self, Read};
+use std::fs;
 use std::borrow::Cow;
 
 use mio;
@@ -12,6 +15,7 @@ use tokio_core::reactor::{PollEvented, Handle};
 use futures;
 use void::Void;
 use libc;
+use dbus;
 
 use common;
 use common::{Event, AxisID, ButtonID};
@@ -50,129 +54,956 @@ impl mio::Evented for Context {
     }
 }
 
-struct Device(libevdev::Device);
+/// A device appearing or disappearing, as reported by a `DeviceMonitor`. This
+/// is the backend-neutral shape `Stream` consumes, whatever discovered it.
+pub enum DeviceNotification {
+    Added { sysname: CString, devnode: CString },
+    Removed { sysname: CString },
+}
+
+/// Device discovery abstracted away from any one OS facility. Both the Linux
+/// udev monitor and the FreeBSD devd socket watch the same `/dev/input/event*`
+/// evdev nodes, so the reader only needs an initial enumeration, a stream of
+/// hotplug notifications, and an fd to wait on.
+pub trait DeviceMonitor: mio::Evented {
+    /// The devices already present when monitoring starts.
+    fn enumerate(&self) -> io::Result<Vec<DeviceNotification>>;
+
+    /// Drain whatever hotplug notifications have buffered, without blocking.
+    fn notifications(&self) -> Vec<DeviceNotification>;
+}
+
+impl DeviceMonitor for Context {
+    fn enumerate(&self) -> io::Result<Vec<DeviceNotification>> {
+        let udev = try!(udev::Context::new());
+        let mut enumerate = try!(udev::Enumerate::new(&udev));
+        try!(enumerate.add_match_subsystem(CStr::from_bytes_with_nul(b"input\0").unwrap()));
+        let mut out = Vec::new();
+        for device in enumerate {
+            match device.devnode() {
+                Some(node) => out.push(DeviceNotification::Added {
+                    sysname: device.sysname().to_owned(),
+                    devnode: node.to_owned(),
+                }),
+                None => debug!("unable to open {} as it has no devnode", device.sysname().to_string_lossy()),
+            }
+        }
+        Ok(out)
+    }
+
+    fn notifications(&self) -> Vec<DeviceNotification> {
+        let mut out = Vec::new();
+        loop {
+            match self.udev.receive_device() {
+                None => break,
+                Some(dev) => match dev.action().to_bytes() {
+                    b"add" => match dev.devnode() {
+                        Some(node) => out.push(DeviceNotification::Added {
+                            sysname: dev.sysname().to_owned(),
+                            devnode: node.to_owned(),
+                        }),
+                        None => debug!("unable to open {} as it has no devnode", dev.sysname().to_string_lossy()),
+                    },
+                    b"remove" => out.push(DeviceNotification::Removed { sysname: dev.sysname().to_owned() }),
+                    x => warn!("unknown libudev action type {:?}", x),
+                },
+            }
+        }
+        out
+    }
+}
+
+/// Device discovery on FreeBSD, where there is no udev: watch the `devd`
+/// notification socket for DEVFS CDEV create/destroy lines and seed the
+/// initial set by scanning `/dev/input`.
+pub struct DevdMonitor {
+    socket: UnixStream,
+    /// Bytes read from the socket that have not yet formed a complete line.
+    pending: RefCell<Vec<u8>>,
+}
+
+impl DevdMonitor {
+    /// The socket devd publishes its notifications on.
+    const SOCKET: &'static str = "/var/run/devd.pipe";
+
+    pub fn new() -> io::Result<DevdMonitor> {
+        let socket = try!(UnixStream::connect(DevdMonitor::SOCKET));
+        try!(socket.set_nonblocking(true));
+        Ok(DevdMonitor { socket: socket, pending: RefCell::new(Vec::new()) })
+    }
+}
+
+impl DeviceMonitor for DevdMonitor {
+    fn enumerate(&self) -> io::Result<Vec<DeviceNotification>> {
+        let mut out = Vec::new();
+        for entry in try!(fs::read_dir("/dev/input")) {
+            let entry = try!(entry);
+            let name = entry.file_name();
+            if !name.as_bytes().starts_with(b"event") {
+                continue;
+            }
+            let sysname = match CString::new(name.as_bytes()) {
+                Ok(s) => s,
+                Err(_) => continue,
+            };
+            let devnode = match CString::new(entry.path().as_os_str().as_bytes()) {
+                Ok(s) => s,
+                Err(_) => continue,
+            };
+            out.push(DeviceNotification::Added { sysname: sysname, devnode: devnode });
+        }
+        Ok(out)
+    }
+
+    fn notifications(&self) -> Vec<DeviceNotification> {
+        let mut out = Vec::new();
+        let mut pending = self.pending.borrow_mut();
+        let mut buf = [0u8; 4096];
+        loop {
+            match (&self.socket).read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => pending.extend_from_slice(&buf[..n]),
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    debug!("devd socket read failed: {}", e.description());
+                    break;
+                },
+            }
+        }
+        while let Some(pos) = pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = pending.drain(..pos + 1).collect();
+            if let Ok(text) = ::std::str::from_utf8(&line[..line.len() - 1]) {
+                if let Some(note) = parse_devd_line(text) {
+                    out.push(note);
+                }
+            }
+        }
+        out
+    }
+}
+
+impl mio::Evented for DevdMonitor {
+    fn register(&self, poll: &mio::Poll, token: mio::Token,
+                interest: mio::Ready, opts: mio::PollOpt) -> ::std::io::Result<()> {
+        mio::unix::EventedFd(&self.socket.as_raw_fd()).register(poll, token, interest, opts)
+    }
+
+    fn reregister(&self, poll: &mio::Poll, token: mio::Token,
+                  interest: mio::Ready, opts: mio::PollOpt) -> ::std::io::Result<()> {
+        mio::unix::EventedFd(&self.socket.as_raw_fd()).reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &mio::Poll) -> ::std::io::Result<()> {
+        mio::unix::EventedFd(&self.socket.as_raw_fd()).deregister(poll)
+    }
+}
+
+/// Parse one devd notification line, e.g.
+/// `!system=DEVFS subsystem=CDEV type=CREATE cdev=input/event5`, into the
+/// matching add/remove notification. Anything that is not an input CDEV
+/// event yields `None`.
+fn parse_devd_line(line: &str) -> Option<DeviceNotification> {
+    if !line.starts_with('!') {
+        return None;
+    }
+    let (mut system, mut subsystem, mut kind, mut cdev) = (None, None, None, None);
+    for token in line[1..].split_whitespace() {
+        let mut kv = token.splitn(2, '=');
+        match (kv.next(), kv.next()) {
+            (Some("system"), Some(v)) => system = Some(v),
+            (Some("subsystem"), Some(v)) => subsystem = Some(v),
+            (Some("type"), Some(v)) => kind = Some(v),
+            (Some("cdev"), Some(v)) => cdev = Some(v),
+            _ => (),
+        }
+    }
+    if system != Some("DEVFS") || subsystem != Some("CDEV") {
+        return None;
+    }
+    let cdev = match cdev {
+        Some(c) if c.starts_with("input/event") => c,
+        _ => return None,
+    };
+    let sysname = match CString::new(cdev.rsplit('/').next().unwrap()) {
+        Ok(s) => s,
+        Err(_) => return None,
+    };
+    match kind {
+        Some("CREATE") => match CString::new(format!("/dev/{}", cdev)) {
+            Ok(devnode) => Some(DeviceNotification::Added { sysname: sysname, devnode: devnode }),
+            Err(_) => None,
+        },
+        Some("DESTROY") => Some(DeviceNotification::Removed { sysname: sysname }),
+        _ => None,
+    }
+}
+
+/// Wraps a `Session`'s event fd (e.g. the logind D-Bus connection) so the
+/// reactor wakes us for pause/resume signals, rather than only when some
+/// device or the monitor happens to be ready.
+struct SessionFd(RawFd);
+
+impl mio::Evented for SessionFd {
+    fn register(&self, poll: &mio::Poll, token: mio::Token,
+                interest: mio::Ready, opts: mio::PollOpt) -> ::std::io::Result<()> {
+        mio::unix::EventedFd(&self.0).register(poll, token, interest, opts)
+    }
+
+    fn reregister(&self, poll: &mio::Poll, token: mio::Token,
+                  interest: mio::Ready, opts: mio::PollOpt) -> ::std::io::Result<()> {
+        mio::unix::EventedFd(&self.0).reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &mio::Poll) -> ::std::io::Result<()> {
+        mio::unix::EventedFd(&self.0).deregister(poll)
+    }
+}
+
+/// Adapts a boxed `DeviceMonitor` so tokio's `PollEvented` can drive whichever
+/// discovery backend the `Stream` was built with.
+struct BoxedMonitor(Box<DeviceMonitor>);
+
+impl mio::Evented for BoxedMonitor {
+    fn register(&self, poll: &mio::Poll, token: mio::Token,
+                interest: mio::Ready, opts: mio::PollOpt) -> ::std::io::Result<()> {
+        self.0.register(poll, token, interest, opts)
+    }
+
+    fn reregister(&self, poll: &mio::Poll, token: mio::Token,
+                  interest: mio::Ready, opts: mio::PollOpt) -> ::std::io::Result<()> {
+        self.0.reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &mio::Poll) -> ::std::io::Result<()> {
+        self.0.deregister(poll)
+    }
+}
+
+/// Out-of-band notification from a `Session` about a device's availability,
+/// independent of the evdev stream itself.
+pub enum SessionEvent {
+    /// The seat manager revoked access to a device (typically a VT switch).
+    /// The fd must not be read until a matching `Resumed` arrives.
+    Paused { major: u32, minor: u32 },
+    /// Access to a device was restored; `fd` is a fresh descriptor for it.
+    Resumed { major: u32, minor: u32, fd: RawFd },
+    /// The session became active again and should re-enumerate its devices.
+    Activated,
+}
+
+/// How `wair` acquires and surrenders device descriptors. The default
+/// `DirectSession` opens nodes itself, while `LogindSession` defers to
+/// systemd-logind so the process can run unprivileged and survive VT
+/// switching.
+pub trait Session {
+    /// Open `path` and return a non-blocking read descriptor for it.
+    fn open(&self, path: &CStr) -> io::Result<RawFd>;
+
+    /// Release the seat manager's reference to a device we are dropping. The
+    /// descriptor itself is always closed by `Device::drop`; this only covers
+    /// the out-of-band bookkeeping (logind's `ReleaseDevice`).
+    #[allow(unused_variables)]
+    fn close(&self, major: u32, minor: u32) {}
+
+    /// Acknowledge that we have stopped reading a paused device, as logind's
+    /// `PauseDevice`/`PauseDeviceComplete` handshake requires. No-op by
+    /// default for sessions that do not arbitrate access.
+    #[allow(unused_variables)]
+    fn pause_complete(&self, major: u32, minor: u32) {}
+
+    /// Drain any pending pause/resume/activation notifications.
+    fn poll_events(&self) -> Vec<SessionEvent> { Vec::new() }
+
+    /// The descriptor to register for readiness when the session is event
+    /// driven (e.g. the D-Bus connection's fd), or `None` otherwise.
+    fn as_raw_fd(&self) -> Option<RawFd> { None }
+}
+
+/// Open device nodes directly with `libc::open`, as `wair` has always done.
+/// Requires the process to be root or a member of the `input` group.
+pub struct DirectSession;
+
+impl DirectSession {
+    pub fn new() -> DirectSession { DirectSession }
+}
+
+impl Session for DirectSession {
+    fn open(&self, path: &CStr) -> io::Result<RawFd> {
+        let fd = unsafe { libc::open(path.as_ptr(), libc::O_RDONLY | libc::O_NONBLOCK) };
+        if fd == -1 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(fd)
+        }
+    }
+
+}
+
+/// Acquire device descriptors through systemd-logind over D-Bus, so the
+/// process can run inside a compositor without the `input` group and keep
+/// working across VT switches.
+pub struct LogindSession {
+    bus: dbus::Connection,
+    /// Object path of our logind session, e.g. `/org/freedesktop/login1/session/_32`.
+    session: String,
+}
+
+impl LogindSession {
+    /// Connect to the system bus and locate the caller's logind session.
+    pub fn new() -> io::Result<LogindSession> {
+        let bus = try!(dbus::Connection::get_private(dbus::BusType::System)
+                       .map_err(dbus_to_io));
+        let pid = unsafe { libc::getpid() } as u32;
+        let msg = dbus::Message::new_method_call(
+            "org.freedesktop.login1", "/org/freedesktop/login1",
+            "org.freedesktop.login1.Manager", "GetSessionByPID").unwrap()
+            .append1(pid);
+        let reply = try!(bus.send_with_reply_and_block(msg, 1000).map_err(dbus_to_io));
+        let session: dbus::Path = try!(reply.get1().ok_or_else(
+            || io::Error::new(io::ErrorKind::NotFound, "no logind session for this process")));
+        let session = session.to_string();
+        let me = LogindSession { bus: bus, session: session };
+        try!(me.take_control());
+        try!(me.watch_signals());
+        Ok(me)
+    }
+
+    fn call(&self, method: &str, msg: dbus::Message) -> io::Result<dbus::Message> {
+        let _ = method;
+        self.bus.send_with_reply_and_block(msg, 1000).map_err(dbus_to_io)
+    }
+
+    /// Become the session controller so logind will mediate device access.
+    fn take_control(&self) -> io::Result<()> {
+        let msg = dbus::Message::new_method_call(
+            "org.freedesktop.login1", &self.session[..],
+            "org.freedesktop.login1.Session", "TakeControl").unwrap()
+            .append1(false);
+        try!(self.call("TakeControl", msg));
+        Ok(())
+    }
+
+    /// Subscribe to `PauseDevice`/`ResumeDevice` and session activation.
+    fn watch_signals(&self) -> io::Result<()> {
+        for member in &["PauseDevice", "ResumeDevice"] {
+            let rule = format!("type='signal',interface='org.freedesktop.login1.Session',\
+                                member='{}',path='{}'", member, self.session);
+            try!(self.bus.add_match(&rule).map_err(dbus_to_io));
+        }
+        let rule = format!("type='signal',interface='org.freedesktop.DBus.Properties',\
+                            member='PropertiesChanged',path='{}'", self.session);
+        try!(self.bus.add_match(&rule).map_err(dbus_to_io));
+        Ok(())
+    }
+}
+
+impl Session for LogindSession {
+    fn open(&self, path: &CStr) -> io::Result<RawFd> {
+        let (major, minor) = try!(device_number(path));
+        let msg = dbus::Message::new_method_call(
+            "org.freedesktop.login1", &self.session[..],
+            "org.freedesktop.login1.Session", "TakeDevice").unwrap()
+            .append2(major, minor);
+        let reply = try!(self.call("TakeDevice", msg));
+        // logind returns the fd plus whether it was inactive; dup it so our
+        // lifetime is independent of the D-Bus message.
+        let fd: dbus::OwnedFd = try!(reply.get1().ok_or_else(
+            || io::Error::new(io::ErrorKind::Other, "TakeDevice returned no fd")));
+        let dup = unsafe { libc::dup(fd.as_raw_fd()) };
+        if dup == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        unsafe { libc::fcntl(dup, libc::F_SETFL, libc::O_NONBLOCK); };
+        Ok(dup)
+    }
+
+    fn close(&self, major: u32, minor: u32) {
+        let msg = dbus::Message::new_method_call(
+            "org.freedesktop.login1", &self.session[..],
+            "org.freedesktop.login1.Session", "ReleaseDevice").unwrap()
+            .append2(major, minor);
+        let _ = self.call("ReleaseDevice", msg);
+    }
+
+    fn pause_complete(&self, major: u32, minor: u32) {
+        let msg = dbus::Message::new_method_call(
+            "org.freedesktop.login1", &self.session[..],
+            "org.freedesktop.login1.Session", "PauseDeviceComplete").unwrap()
+            .append2(major, minor);
+        let _ = self.call("PauseDeviceComplete", msg);
+    }
+
+    fn poll_events(&self) -> Vec<SessionEvent> {
+        let mut out = Vec::new();
+        // Non-blocking drain of whatever the D-Bus connection has buffered.
+        for item in self.bus.incoming(0) {
+            if let dbus::ConnectionItem::Signal(ref msg) = item {
+                match (msg.interface(), msg.member()) {
+                    (Some(ref i), Some(ref m))
+                        if &**i == "org.freedesktop.login1.Session" && &**m == "PauseDevice" => {
+                        if let (Some(major), Some(minor)) = msg.get2() {
+                            out.push(SessionEvent::Paused { major: major, minor: minor });
+                        }
+                    },
+                    (Some(ref i), Some(ref m))
+                        if &**i == "org.freedesktop.login1.Session" && &**m == "ResumeDevice" => {
+                        if let (Some(major), Some(minor), Some(fd)) = msg.get3::<u32, u32, dbus::OwnedFd>() {
+                            let dup = unsafe { libc::dup(fd.as_raw_fd()) };
+                            if dup != -1 {
+                                unsafe { libc::fcntl(dup, libc::F_SETFL, libc::O_NONBLOCK); };
+                                out.push(SessionEvent::Resumed { major: major, minor: minor, fd: dup });
+                            }
+                        }
+                    },
+                    (Some(ref i), Some(ref m))
+                        if &**i == "org.freedesktop.DBus.Properties" && &**m == "PropertiesChanged" => {
+                        // logind emits PropertiesChanged for unrelated props
+                        // (IdleHint, LockedHint, ...); only a genuine
+                        // Active -> true warrants re-enumerating devices.
+                        if session_became_active(msg) {
+                            out.push(SessionEvent::Activated);
+                        }
+                    },
+                    _ => (),
+                }
+            }
+        }
+        out
+    }
+
+    fn as_raw_fd(&self) -> Option<RawFd> {
+        self.bus.watch_fds().into_iter().next().map(|w| w.fd())
+    }
+}
+
+/// Inspect a session `PropertiesChanged` payload and report whether it carries
+/// `Active` transitioning to `true`. The signal's arguments are
+/// `(interface, changed: a{sv}, invalidated: as)`; we only act on the session
+/// interface's `Active` property so unrelated property churn is ignored.
+fn session_became_active(msg: &dbus::Message) -> bool {
+    use dbus::arg::{Iter, Variant, Dict};
+    let mut iter = msg.iter_init();
+    let interface: String = match iter.read() {
+        Ok(s) => s,
+        Err(_) => return false,
+    };
+    if interface != "org.freedesktop.login1.Session" {
+        return false;
+    }
+    let changed: Dict<String, Variant<Iter>, _> = match iter.read() {
+        Ok(d) => d,
+        Err(_) => return false,
+    };
+    for (key, value) in changed {
+        if key == "Active" {
+            let mut variant = value.0;
+            return variant.read::<bool>().unwrap_or(false);
+        }
+    }
+    false
+}
+
+/// Translate a D-Bus error into the `io::Error` surface the rest of the
+/// module already speaks.
+fn dbus_to_io(e: dbus::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other,
+                   e.message().unwrap_or("dbus error").to_owned())
+}
+
+/// Derive the `(major, minor)` numbers logind keys devices by from a devnode.
+fn device_number(path: &CStr) -> io::Result<(u32, u32)> {
+    let mut st: libc::stat = unsafe { ::std::mem::zeroed() };
+    if unsafe { libc::stat(path.as_ptr(), &mut st) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let rdev = st.st_rdev;
+    let major = unsafe { libc::major(rdev) } as u32;
+    let minor = unsafe { libc::minor(rdev) } as u32;
+    Ok((major, minor))
+}
+
+/// Decides whether a device is worth opening, matched against its libevdev
+/// capabilities and identity, so callers only spend fds on devices they care
+/// about. Construct one with a convenience constructor or [`custom`].
+///
+/// [`custom`]: DeviceFilter::custom
+pub struct DeviceFilter {
+    predicate: Box<Fn(&libevdev::Device) -> bool>,
+}
+
+impl DeviceFilter {
+    /// Accept every device. This is the default when no filter is configured.
+    pub fn all() -> DeviceFilter {
+        DeviceFilter::custom(|_| true)
+    }
+
+    /// Accept devices for which `predicate` returns `true`, given full access
+    /// to the opened libevdev device (capabilities and `input_id`).
+    pub fn custom<F>(predicate: F) -> DeviceFilter
+        where F: Fn(&libevdev::Device) -> bool + 'static {
+        DeviceFilter { predicate: Box::new(predicate) }
+    }
+
+    /// Keyboards: devices that report `KEY_ENTER`.
+    pub fn keyboards() -> DeviceFilter {
+        DeviceFilter::custom(|d| d.has_event_code(codes::EV_KEY as u32, codes::KEY_ENTER as u32))
+    }
+
+    /// Pointers: devices that report `BTN_LEFT`.
+    pub fn pointers() -> DeviceFilter {
+        DeviceFilter::custom(|d| d.has_event_code(codes::EV_KEY as u32, codes::BTN_LEFT as u32))
+    }
+
+    /// Joysticks: absolute axes plus `BTN_JOYSTICK`. Gamepads that report
+    /// only `BTN_GAMEPAD`-range buttons are not matched.
+    pub fn joysticks() -> DeviceFilter {
+        DeviceFilter::custom(|d| d.has_event_type(codes::EV_ABS as u32)
+                             && d.has_event_code(codes::EV_KEY as u32, codes::BTN_JOYSTICK as u32))
+    }
+
+    /// Multitouch devices: absolute axes with `ABS_MT_SLOT`.
+    pub fn multitouch() -> DeviceFilter {
+        DeviceFilter::custom(|d| d.has_event_code(codes::EV_ABS as u32, codes::ABS_MT_SLOT as u32))
+    }
+
+    /// Devices whose name contains `substring`.
+    pub fn named(substring: &str) -> DeviceFilter {
+        let needle = substring.to_owned();
+        DeviceFilter::custom(move |d| d.get_name().to_string_lossy().contains(&needle))
+    }
+
+    /// Devices matching a specific USB-style `vendor`/`product` pair.
+    pub fn vendor_product(vendor: u16, product: u16) -> DeviceFilter {
+        DeviceFilter::custom(move |d| d.get_id_vendor() == vendor && d.get_id_product() == product)
+    }
+
+    fn accepts(&self, device: &libevdev::Device) -> bool {
+        (self.predicate)(device)
+    }
+}
+
+/// Calibration metadata for an absolute axis, mirroring libevdev's absinfo.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AbsInfo {
+    pub minimum: i32,
+    pub maximum: i32,
+    pub flat: i32,
+    pub fuzz: i32,
+    pub resolution: i32,
+}
+
+impl AbsInfo {
+    /// Scale a raw reading into `[-1.0, 1.0]` for axes whose range straddles
+    /// zero, or `[0.0, 1.0]` for single-ended axes, collapsing readings within
+    /// `flat` of the centre (of the minimum, for single-ended axes) to an
+    /// exact zero.
+    fn normalize(&self, value: i32) -> f64 {
+        let min = self.minimum as f64;
+        let max = self.maximum as f64;
+        if max <= min {
+            return 0.0;
+        }
+        if self.minimum < 0 {
+            let mid = (min + max) / 2.0;
+            let half = (max - min) / 2.0;
+            let centered = value as f64 - mid;
+            if centered.abs() <= self.flat as f64 {
+                return 0.0;
+            }
+            (centered / half).max(-1.0).min(1.0)
+        } else {
+            // Single-ended axes rest at their minimum, so the deadzone is
+            // measured from there rather than from a centre.
+            if (value as f64 - min) <= self.flat as f64 {
+                return 0.0;
+            }
+            ((value as f64 - min) / (max - min)).max(0.0).min(1.0)
+        }
+    }
+}
+
+struct Device {
+    evdev: libevdev::Device,
+    /// Last logical value we reported to consumers for every supported
+    /// stateful code, keyed by `(type, code)`. Used to reconcile our view
+    /// with the kernel's after a `SYN_DROPPED`.
+    state: HashMap<(u16, u16), i32>,
+    /// absinfo for every supported absolute code, keyed by `ABS_*` code, so
+    /// `map_device_event` can normalize without re-querying libevdev.
+    absinfo: HashMap<u16, AbsInfo>,
+    /// `true` once `next_event` has reported a dropped packet and we are
+    /// draining the `READ_FLAG_SYNC` recovery stream.
+    syncing: bool,
+    /// `(major, minor)` of the devnode, used to match logind pause/resume
+    /// signals and to release the device when it is dropped.
+    devnum: (u32, u32),
+    /// `true` while the seat manager has revoked access; the device must not
+    /// be read until it is resumed.
+    paused: bool,
+    /// `true` while we hold an exclusive `EVIOCGRAB` on the device.
+    grabbed: bool,
+}
+
+/// `EVIOCGRAB` — `_IOW('E', 0x90, int)` — the ioctl that gives a single
+/// client exclusive access to an evdev node.
+const EVIOCGRAB: libc::c_ulong = 0x4004_4590;
 
 impl Device {
-    fn new(evdev: libevdev::Device) -> Self {
+    fn new(evdev: libevdev::Device, devnum: (u32, u32)) -> Self {
         trace!("opened \"{}\"", evdev.get_name().to_string_lossy());
-        Device(evdev)
+        let state = snapshot_state(&evdev);
+        let absinfo = snapshot_absinfo(&evdev);
+        Device {
+            evdev: evdev,
+            state: state,
+            absinfo: absinfo,
+            syncing: false,
+            devnum: devnum,
+            paused: false,
+            grabbed: false,
+        }
+    }
+
+    /// Claim the device exclusively so its events stop reaching the rest of
+    /// the system (X11/Wayland), which is what a remapper wants.
+    fn grab(&mut self) -> io::Result<()> {
+        if unsafe { libc::ioctl(self.evdev.as_raw_fd(), EVIOCGRAB, 1 as libc::c_int) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        self.grabbed = true;
+        Ok(())
+    }
+
+    /// Drop the exclusive grab, letting events flow to other clients again.
+    fn ungrab(&mut self) -> io::Result<()> {
+        if unsafe { libc::ioctl(self.evdev.as_raw_fd(), EVIOCGRAB, 0 as libc::c_int) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        self.grabbed = false;
+        Ok(())
     }
 }
 
 impl Drop for Device {
     fn drop(&mut self) {
-        unsafe { libc::close(self.0.as_raw_fd()); };
+        if self.grabbed {
+            unsafe { libc::ioctl(self.evdev.as_raw_fd(), EVIOCGRAB, 0 as libc::c_int); };
+        }
+        unsafe { libc::close(self.evdev.as_raw_fd()); };
     }
 }
 
 impl mio::Evented for Device {
     fn register(&self, poll: &mio::Poll, token: mio::Token,
                 interest: mio::Ready, opts: mio::PollOpt) -> ::std::io::Result<()> {
-        mio::unix::EventedFd(&self.0.as_raw_fd()).register(poll, token, interest, opts)
+        mio::unix::EventedFd(&self.evdev.as_raw_fd()).register(poll, token, interest, opts)
     }
 
     fn reregister(&self, poll: &mio::Poll, token: mio::Token,
                   interest: mio::Ready, opts: mio::PollOpt) -> ::std::io::Result<()> {
-        mio::unix::EventedFd(&self.0.as_raw_fd()).reregister(poll, token, interest, opts)
+        mio::unix::EventedFd(&self.evdev.as_raw_fd()).reregister(poll, token, interest, opts)
     }
 
     fn deregister(&self, poll: &mio::Poll) -> ::std::io::Result<()> {
-        mio::unix::EventedFd(&self.0.as_raw_fd()).deregister(poll)
+        mio::unix::EventedFd(&self.evdev.as_raw_fd()).deregister(poll)
+    }
+}
+
+/// Snapshot the current value of every supported `EV_KEY`/`EV_ABS`/`EV_SW`
+/// code so a later `SYN_DROPPED` can be reconciled against it.
+fn snapshot_state(evdev: &libevdev::Device) -> HashMap<(u16, u16), i32> {
+    let mut state = HashMap::new();
+    for &(ty, cnt) in &[(codes::EV_KEY, codes::KEY_CNT),
+                        (codes::EV_ABS, codes::ABS_CNT),
+                        (codes::EV_SW, codes::SW_CNT)] {
+        if !evdev.has_event_type(ty as u32) {
+            continue;
+        }
+        for code in 0..cnt {
+            if evdev.has_event_code(ty as u32, code as u32) {
+                state.insert((ty as u16, code as u16),
+                             evdev.get_event_value(ty as u32, code as u32));
+            }
+        }
+    }
+    state
+}
+
+/// Cache the absinfo of every supported absolute code so callers and the event
+/// mapper can normalize analog readings without re-querying libevdev.
+fn snapshot_absinfo(evdev: &libevdev::Device) -> HashMap<u16, AbsInfo> {
+    let mut map = HashMap::new();
+    if !evdev.has_event_type(codes::EV_ABS as u32) {
+        return map;
+    }
+    for code in 0..codes::ABS_CNT {
+        if !evdev.has_event_code(codes::EV_ABS as u32, code as u32) {
+            continue;
+        }
+        if let Some(info) = evdev.get_abs_info(code as u32) {
+            map.insert(code as u16, AbsInfo {
+                minimum: info.minimum,
+                maximum: info.maximum,
+                flat: info.flat,
+                fuzz: info.fuzz,
+                resolution: info.resolution,
+            });
+        }
     }
+    map
 }
 
 pub struct Stream {
-    udev: PollEvented<Context>,
+    monitor: PollEvented<BoxedMonitor>,
     tokio: Handle,
+    session: Box<Session>,
+    /// Readiness handle for the session's event fd, when it has one, so
+    /// logind pause/resume signals wake the reactor on their own.
+    session_poll: Option<PollEvented<SessionFd>>,
     devices: RefCell<HashMap<CString, PollEvented<Device>>>,
     buffer: RefCell<VecDeque<Event<WindowID, DeviceID>>>,
+    /// Grab every device as it is opened, for full-screen apps and remappers.
+    grab_new: bool,
+    /// Devices the caller has explicitly grabbed, so the grab is re-applied
+    /// when one is hot-plugged back in.
+    grabbed: RefCell<HashSet<CString>>,
+    /// Decides which devices are opened at enumeration and hotplug time.
+    filter: DeviceFilter,
+    /// Whether the initial enumeration has run; deferred to the first poll so
+    /// builder configuration (filter, grabbing) is applied first.
+    enumerated: RefCell<bool>,
 }
 
 impl Stream {
+    /// Open the evdev stream, acquiring device descriptors through the default
+    /// `DirectSession`. Use [`with_session`](Stream::with_session) to run under
+    /// logind instead.
     pub fn new(handle: &Handle) -> Result<Self, String> {
-        let inner = try!(from_result(Context::new()));
-        let poll = try!(from_result(PollEvented::new(inner, handle)));
+        Stream::with_session(handle, Box::new(DirectSession::new()))
+    }
+
+    /// Open the evdev stream, acquiring device descriptors through `session`
+    /// and discovering devices through the udev monitor ([`Context`]).
+    pub fn with_session(handle: &Handle, session: Box<Session>) -> Result<Self, String> {
+        let monitor = try!(from_result(Context::new()));
+        Stream::with_monitor(handle, Box::new(monitor), session)
+    }
+
+    /// Open the evdev stream with an explicit discovery backend, e.g. a
+    /// [`DevdMonitor`] on FreeBSD.
+    pub fn with_monitor(handle: &Handle, monitor: Box<DeviceMonitor>, session: Box<Session>)
+                        -> Result<Self, String> {
+        let poll = try!(from_result(PollEvented::new(BoxedMonitor(monitor), handle)));
+        let session_poll = match session.as_raw_fd() {
+            Some(fd) => Some(try!(from_result(PollEvented::new(SessionFd(fd), handle)))),
+            None => None,
+        };
 
         let result = Stream {
-            udev: poll,
+            monitor: poll,
             tokio: handle.clone(),
+            session: session,
+            session_poll: session_poll,
             devices: RefCell::new(HashMap::new()),
             buffer: RefCell::new(VecDeque::new()),
+            grab_new: false,
+            grabbed: RefCell::new(HashSet::new()),
+            filter: DeviceFilter::all(),
+            enumerated: RefCell::new(false),
         };
 
-        try!(result.open_existing_devices(&result.udev.get_ref().udev).map_err(|e| e.description().to_string()));
-
         Ok(result)
     }
 
-    fn map_udev_event(&self, device: udev::Device) -> Option<Event<WindowID, DeviceID>> {
-        match device.action().to_bytes() {
-            b"add" => {
-                match device.devnode() {
-                    None => {
-                        debug!("unable to open {} as it has no devnode", device.sysname().to_string_lossy());
-                        None
-                    },
-                    Some(node) => {
-                        match self.open_device(device.sysname(), node) {
+    /// Only open devices accepted by `filter`; the rest are ignored at both
+    /// enumeration and hotplug time and never produce `DeviceAdded`.
+    pub fn filtering(mut self, filter: DeviceFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Grab every device exclusively as it appears, so events reach only
+    /// `wair`. Equivalent to calling [`grab`](Stream::grab) on each device.
+    pub fn grabbing(mut self, yes: bool) -> Self {
+        self.grab_new = yes;
+        self
+    }
+
+    /// Take an exclusive `EVIOCGRAB` on `device`; its events stop reaching
+    /// X11/Wayland until it is ungrabbed or removed.
+    pub fn grab(&self, device: &DeviceID) -> io::Result<()> {
+        try!(self.with_device(device, Device::grab));
+        self.grabbed.borrow_mut().insert(device.0.clone());
+        Ok(())
+    }
+
+    /// Release a grab taken with [`grab`](Stream::grab).
+    pub fn ungrab(&self, device: &DeviceID) -> io::Result<()> {
+        self.grabbed.borrow_mut().remove(&device.0);
+        self.with_device(device, Device::ungrab)
+    }
+
+    fn with_device<F>(&self, device: &DeviceID, f: F) -> io::Result<()>
+        where F: FnOnce(&mut Device) -> io::Result<()> {
+        match self.devices.borrow_mut().get_mut(&device.0) {
+            Some(poll) => f(poll.get_mut()),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, "no such device")),
+        }
+    }
+
+    /// Retrieve the cached absinfo for an absolute `axis` of `device`, letting
+    /// consumers recover the raw range/deadzone/resolution behind a normalized
+    /// `RawAbsMotion`. Returns `None` for unknown devices or relative axes.
+    pub fn abs_info(&self, device: &DeviceID, axis: AxisID) -> Option<AbsInfo> {
+        if axis.0 < codes::REL_CNT as u32 {
+            return None;
+        }
+        let code = (axis.0 - codes::REL_CNT as u32) as u16;
+        self.devices.borrow().get(&device.0).and_then(|d| d.get_ref().absinfo.get(&code).cloned())
+    }
+
+    /// Look up the `sysname` of the device currently backed by `(major, minor)`.
+    fn device_for(&self, major: u32, minor: u32) -> Option<CString> {
+        self.devices.borrow().iter()
+            .find(|&(_, poll)| poll.get_ref().devnum == (major, minor))
+            .map(|(id, _)| id.clone())
+    }
+
+    /// Apply the session's pending pause/resume/activation notifications,
+    /// pushing the corresponding `DevicePaused`/`DeviceResumed` events.
+    fn drain_session(&self, buffer: &mut VecDeque<Event<WindowID, DeviceID>>) {
+        for event in self.session.poll_events() {
+            match event {
+                SessionEvent::Paused { major, minor } => {
+                    if let Some(id) = self.device_for(major, minor) {
+                        if let Some(poll) = self.devices.borrow_mut().get_mut(&id) {
+                            poll.get_mut().paused = true;
+                            // Stop the reactor from waking us for the revoked
+                            // fd; resume rebuilds the `PollEvented`, re-arming
+                            // it. Otherwise a HUP'd fd spins `poll` every cycle.
+                            if let Err(e) = poll.deregister(&self.tokio) {
+                                debug!("unable to deregister paused device: {}", e.description());
+                            }
+                        }
+                        // logind will not reassign the device until we confirm.
+                        self.session.pause_complete(major, minor);
+                        buffer.push_back(Event::DevicePaused { device: DeviceID(id) });
+                    } else {
+                        self.session.pause_complete(major, minor);
+                    }
+                },
+                SessionEvent::Resumed { major, minor, fd } => {
+                    // The old descriptor was revoked, so rebuild the device
+                    // around the fresh fd logind handed us and re-arm it.
+                    match self.device_for(major, minor) {
+                        Some(id) => match libevdev::Device::new_from_fd(fd) {
+                            Ok(d) => {
+                                let mut dev = Device::new(d, (major, minor));
+                                if self.grab_new || self.grabbed.borrow().contains(&id) {
+                                    if let Err(e) = dev.grab() {
+                                        debug!("unable to re-grab resumed {}: {}", id.to_string_lossy(), e.description());
+                                    }
+                                }
+                                match PollEvented::new(dev, &self.tokio) {
+                                    Ok(poll) => {
+                                        self.devices.borrow_mut().insert(id.clone(), poll);
+                                        buffer.push_back(Event::DeviceResumed { device: DeviceID(id) });
+                                    },
+                                    // `dev` already owns `fd`; dropping it here
+                                    // closes the descriptor, so we must not.
+                                    Err(e) => debug!("unable to re-arm resumed device: {}", e.description()),
+                                }
+                            },
                             Err(e) => {
-                                debug!("unable to open {}: {}", node.to_string_lossy(), e.description());
-                                None
+                                unsafe { libc::close(fd); };
+                                debug!("unable to re-open resumed device: {}", e.description());
                             },
-                            Ok(()) => Some(Event::DeviceAdded { device: DeviceID(device.sysname().to_owned()) })
-                        }
+                        },
+                        None => unsafe { libc::close(fd); },
+                    }
+                },
+                SessionEvent::Activated => {
+                    if let Err(e) = self.open_existing_devices() {
+                        debug!("re-enumeration after session activation failed: {}", e.description());
                     }
+                },
+            }
+        }
+    }
+
+    fn handle_notification(&self, note: DeviceNotification) -> Option<Event<WindowID, DeviceID>> {
+        match note {
+            DeviceNotification::Added { sysname, devnode } => {
+                match self.open_device(&sysname, &devnode) {
+                    Err(e) => {
+                        debug!("unable to open {}: {}", devnode.to_string_lossy(), e.description());
+                        None
+                    },
+                    Ok(false) => None,
+                    Ok(true) => Some(Event::DeviceAdded { device: DeviceID(sysname) }),
                 }
             },
-            b"remove" => {
-                match self.devices.borrow_mut().remove(device.sysname()) {
+            DeviceNotification::Removed { sysname } => {
+                match self.devices.borrow_mut().remove(&sysname) {
                     None => {
-                        debug!("unknown device {} removed", device.sysname().to_string_lossy());
+                        debug!("unknown device {} removed", sysname.to_string_lossy());
                         None
                     },
-                    Some(_) => Some(Event::DeviceRemoved { device: DeviceID(device.sysname().to_owned()) }),
+                    Some(poll) => {
+                        let (major, minor) = poll.get_ref().devnum;
+                        self.session.close(major, minor);
+                        Some(Event::DeviceRemoved { device: DeviceID(sysname) })
+                    },
                 }
             },
-            x => { warn!("unknown libudev action type {:?}", x); None },
         }
     }
 
-    fn open_device(&self, sysname: &CStr, syspath: &CStr) -> io::Result<()> {
-        let fd = unsafe { libc::open(syspath.as_ptr(), libc::O_RDONLY | libc::O_NONBLOCK) };
-        if fd == -1 {
-            Err(io::Error::last_os_error())
-        } else {
-            match libevdev::Device::new_from_fd(fd) {
-                Err(e) => {
-                    unsafe { libc::close(fd); };
-                    Err(e)
-                },
-                Ok(d) => {
-                    let dev = Device::new(d);
-                    let poll = try!(PollEvented::new(dev, &self.tokio));
-                    self.devices.borrow_mut().insert(sysname.to_owned(), poll);
-                    Ok(())
+    /// Open `syspath`, returning `Ok(true)` if the device was kept and
+    /// `Ok(false)` if the configured `DeviceFilter` rejected it.
+    fn open_device(&self, sysname: &CStr, syspath: &CStr) -> io::Result<bool> {
+        let devnum = try!(device_number(syspath));
+        let fd = try!(self.session.open(syspath));
+        match libevdev::Device::new_from_fd(fd) {
+            Err(e) => {
+                unsafe { libc::close(fd); };
+                self.session.close(devnum.0, devnum.1);
+                Err(e)
+            },
+            Ok(d) => {
+                if !self.filter.accepts(&d) {
+                    trace!("ignoring \"{}\": rejected by filter", d.get_name().to_string_lossy());
+                    unsafe { libc::close(d.as_raw_fd()); };
+                    self.session.close(devnum.0, devnum.1);
+                    return Ok(false);
+                }
+                let mut dev = Device::new(d, devnum);
+                // Re-apply an exclusive grab for devices the caller asked to
+                // grab, or for every device when `grab_new` is set.
+                if self.grab_new || self.grabbed.borrow().contains(sysname) {
+                    if let Err(e) = dev.grab() {
+                        debug!("unable to grab {}: {}", sysname.to_string_lossy(), e.description());
+                    }
                 }
+                let poll = try!(PollEvented::new(dev, &self.tokio));
+                self.devices.borrow_mut().insert(sysname.to_owned(), poll);
+                Ok(true)
             }
         }
     }
 
-    fn open_existing_devices(&self, udev: &udev::Context) -> io::Result<()> {
-        let mut enumerate = try!(udev::Enumerate::new(&udev));
-        try!(enumerate.add_match_subsystem(CStr::from_bytes_with_nul(b"input\0").unwrap()));
-        for device in enumerate {
-            match device.devnode() {
-                None => debug!("unable to open {} as it has no devnode", device.sysname().to_string_lossy()),
-                Some(node) => match self.open_device(device.sysname(), node) {
-                    Err(e) => debug!("unable to open {}: {}", node.to_string_lossy(), e.description()),
-                    Ok(()) => (),
-                },
+    fn open_existing_devices(&self) -> io::Result<()> {
+        for note in try!(self.monitor.get_ref().0.enumerate()) {
+            if let DeviceNotification::Added { sysname, devnode } = note {
+                if let Err(e) = self.open_device(&sysname, &devnode) {
+                    debug!("unable to open {}: {}", devnode.to_string_lossy(), e.description());
+                }
             }
         }
         Ok(())
     }
 
-    fn map_device_event(&self, id: &CStr, event: libevdev::InputEvent) -> Option<Event<WindowID, DeviceID>> {
+    fn map_device_event(&self, id: &CStr, event: libevdev::InputEvent, device: &Device) -> Option<Event<WindowID, DeviceID>> {
         match event.type_ {
             codes::EV_SYN => None,
             codes::EV_KEY => match event.value {
@@ -190,11 +1021,23 @@ impl Stream {
                     None
                 },
             },
-            codes::EV_ABS => Some(Event::RawMotion {
-                device: DeviceID(id.to_owned()),
-                axis: AxisID((codes::REL_CNT + event.code) as u32),
-                value: event.value as f64,
-            }),
+            codes::EV_ABS => {
+                let code = event.code as u16;
+                let info = device.absinfo.get(&code).cloned();
+                // Swallow jitter smaller than the axis' fuzz relative to the
+                // last reported value so analog axes stop chattering at rest.
+                if let (Some(i), Some(prev)) = (info, device.state.get(&(codes::EV_ABS as u16, code)).cloned()) {
+                    if (event.value - prev).abs() < i.fuzz {
+                        return None;
+                    }
+                }
+                Some(Event::RawAbsMotion {
+                    device: DeviceID(id.to_owned()),
+                    axis: AxisID(codes::REL_CNT as u32 + code as u32),
+                    value: event.value as f64,
+                    normalized: info.map(|i| i.normalize(event.value)).unwrap_or(event.value as f64),
+                })
+            },
             codes::EV_REL => Some(Event::RawMotion {
                 device: DeviceID(id.to_owned()),
                 axis: AxisID(event.code as u32),
@@ -216,25 +1059,111 @@ impl Stream {
 
     fn poll_device(&self, id: &CStr, device: &mut Device) {
         use super::platform::libevdev::ReadStatus::*;
+        if device.paused {
+            return;
+        }
         let mut buffer = self.buffer.borrow_mut();
         let mut flag = libevdev::READ_FLAG_NORMAL;
         loop {
-            match device.0.next_event(flag) {
-                Again => break,
-                Sync(e) => {
-                    flag = libevdev::READ_FLAG_SYNC;
-                    if let Some(x) = self.map_device_event(id, e) {
-                        buffer.push_back(x)
+            match device.evdev.next_event(flag) {
+                Again => {
+                    if device.syncing {
+                        // The `READ_FLAG_SYNC` recovery stream is exhausted;
+                        // libevdev's per-code values are now the kernel's, so
+                        // reconcile them against our cached snapshot and keep
+                        // reading whatever arrived after the drop.
+                        self.resync_device(id, device, &mut buffer);
+                        device.syncing = false;
+                        flag = libevdev::READ_FLAG_NORMAL;
+                        continue;
+                    }
+                    break;
+                },
+                Sync(_) => {
+                    if !device.syncing {
+                        // A `SYN_DROPPED` was seen: warn stateful consumers that
+                        // this device may have jumped before we replay the diff.
+                        buffer.push_back(Event::DeviceSync { device: DeviceID(id.to_owned()) });
+                        device.syncing = true;
+                        flag = libevdev::READ_FLAG_SYNC;
                     }
+                    // Drop the synthetic event itself; we only need libevdev to
+                    // absorb it so its state reflects the kernel for the diff.
                 },
                 Success(e) => {
-                    if let Some(x) = self.map_device_event(id, e) {
+                    let mapped = self.map_device_event(id, e, device);
+                    match e.type_ {
+                        // Only advance the absolute baseline when we actually
+                        // reported a move, otherwise fuzz would never trigger.
+                        codes::EV_ABS => if mapped.is_some() { self.remember_state(device, e); },
+                        codes::EV_KEY | codes::EV_SW => self.remember_state(device, e),
+                        _ => (),
+                    }
+                    if let Some(x) = mapped {
                         buffer.push_back(x)
                     }
                 },
             }
         }
     }
+
+    /// Record the logical value of a stateful event so the snapshot stays
+    /// current on the normal read path. Relative axes carry no state. Key
+    /// values are clamped to the 0/1 `get_event_value` reports, so an
+    /// auto-repeat (`2`) does not make a held key look changed at the next
+    /// resync.
+    fn remember_state(&self, device: &mut Device, event: libevdev::InputEvent) {
+        let value = if event.type_ == codes::EV_KEY && event.value != 0 { 1 } else { event.value };
+        device.state.insert((event.type_ as u16, event.code as u16), value);
+    }
+
+    /// Diff libevdev's now-current per-code values against our cached snapshot
+    /// and emit the minimal set of events that brings consumers back in line
+    /// with the kernel, updating the snapshot as we go. Unlike the live path,
+    /// the absolute `fuzz` gate is not applied here: a reconciliation reports
+    /// any difference from the snapshot, even a sub-`fuzz` one, since its job
+    /// is to restore exact agreement with the kernel after a drop.
+    fn resync_device(&self, id: &CStr, device: &mut Device,
+                     buffer: &mut VecDeque<Event<WindowID, DeviceID>>) {
+        let codes: Vec<(u16, u16)> = device.state.keys().cloned().collect();
+        for (ty, code) in codes {
+            let current = device.evdev.get_event_value(ty as u32, code as u32);
+            if device.state.get(&(ty, code)).cloned() == Some(current) {
+                continue;
+            }
+            let info = if ty == codes::EV_ABS as u16 { device.absinfo.get(&code).cloned() } else { None };
+            if let Some(e) = self.resync_event(id, ty, code, current, info) {
+                buffer.push_back(e);
+            }
+            device.state.insert((ty, code), current);
+        }
+    }
+
+    /// Map a reconciled `(type, code, value)` to the raw event a live read of
+    /// the same transition would have produced. `EV_SW` carries no raw event.
+    fn resync_event(&self, id: &CStr, ty: u16, code: u16, value: i32, info: Option<AbsInfo>)
+                    -> Option<Event<WindowID, DeviceID>> {
+        match ty as u32 {
+            x if x == codes::EV_KEY as u32 => Some(if value == 0 {
+                Event::RawButtonPress {
+                    device: DeviceID(id.to_owned()),
+                    button: ButtonID(code as u32),
+                }
+            } else {
+                Event::RawButtonRelease {
+                    device: DeviceID(id.to_owned()),
+                    button: ButtonID(code as u32),
+                }
+            }),
+            x if x == codes::EV_ABS as u32 => Some(Event::RawAbsMotion {
+                device: DeviceID(id.to_owned()),
+                axis: AxisID(codes::REL_CNT as u32 + code as u32),
+                value: value as f64,
+                normalized: info.map(|i| i.normalize(value)).unwrap_or(value as f64),
+            }),
+            _ => None,
+        }
+    }
 }
 
 fn from_result<T, E: Error>(x: Result<T, E>) -> Result<T, String> {
@@ -263,8 +1192,25 @@ impl<'a> futures::stream::Stream for &'a Stream {
     type Error = ();
 
     fn poll(&mut self) -> futures::Poll<Option<Self::Item>, Self::Error> {
-        if futures::Async::NotReady == self.udev.poll_read()
-            && self.devices.borrow().values().map(PollEvented::poll_read).all(|x| x == futures::Async::NotReady) {
+        // Enumerate existing devices on the first poll, once builder
+        // configuration (filter, grabbing) has been applied.
+        if !*self.enumerated.borrow() {
+            *self.enumerated.borrow_mut() = true;
+            if let Err(e) = self.open_existing_devices() {
+                debug!("initial device enumeration failed: {}", e.description());
+            }
+        }
+
+        let session_ready = match self.session_poll {
+            Some(ref p) => p.poll_read() != futures::Async::NotReady,
+            None => false,
+        };
+        if !session_ready
+            && futures::Async::NotReady == self.monitor.poll_read()
+            && self.devices.borrow().values()
+                .filter(|p| !p.get_ref().paused)
+                .map(|p| p.poll_read())
+                .all(|x| x == futures::Async::NotReady) {
             return Ok(futures::Async::NotReady);
         }
 
@@ -274,23 +1220,109 @@ impl<'a> futures::stream::Stream for &'a Stream {
 
         let mut buffer = self.buffer.borrow_mut();
 
-        loop {
-            match self.udev.get_ref().udev.receive_device() {
-                None => break,
-                Some(dev) => {
-                    if let Some(e) = self.map_udev_event(dev) {
-                        buffer.push_back(e);
-                    }
-                }
+        // Seat-manager notifications are not tied to the monitor fd, so drain
+        // them on every wake-up alongside the device and discovery reads.
+        self.drain_session(&mut buffer);
+
+        for note in self.monitor.get_ref().0.notifications() {
+            if let Some(e) = self.handle_notification(note) {
+                buffer.push_back(e);
             }
         }
 
         Ok(match buffer.pop_front() {
             None => {
-                self.udev.need_read();
+                self.monitor.need_read();
+                if let Some(ref p) = self.session_poll {
+                    p.need_read();
+                }
                 futures::Async::NotReady
             },
             Some(i) => futures::Async::Ready(Some(i)),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{AbsInfo, DeviceNotification, parse_devd_line};
+    use std::ffi::CString;
+
+    fn absinfo(minimum: i32, maximum: i32, flat: i32) -> AbsInfo {
+        AbsInfo { minimum: minimum, maximum: maximum, flat: flat, fuzz: 0, resolution: 0 }
+    }
+
+    #[test]
+    fn normalize_bipolar_spans_minus_one_to_one() {
+        let info = absinfo(-32768, 32767, 0);
+        assert!((info.normalize(32767) - 1.0).abs() < 1e-3);
+        assert!((info.normalize(-32768) + 1.0).abs() < 1e-3);
+        assert!(info.normalize(0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn normalize_bipolar_flat_is_a_centered_deadzone() {
+        let info = absinfo(-100, 100, 10);
+        assert_eq!(info.normalize(5), 0.0);
+        assert_eq!(info.normalize(-5), 0.0);
+        assert!(info.normalize(55) > 0.0);
+    }
+
+    #[test]
+    fn normalize_single_ended_spans_zero_to_one() {
+        let info = absinfo(0, 255, 0);
+        assert_eq!(info.normalize(0), 0.0);
+        assert!((info.normalize(255) - 1.0).abs() < 1e-3);
+        assert!((info.normalize(128) - 0.501).abs() < 1e-2);
+    }
+
+    #[test]
+    fn normalize_single_ended_flat_is_a_deadzone_from_the_minimum() {
+        let info = absinfo(0, 255, 10);
+        assert_eq!(info.normalize(5), 0.0);
+        assert!(info.normalize(40) > 0.0);
+    }
+
+    #[test]
+    fn normalize_degenerate_range_is_zero() {
+        assert_eq!(absinfo(5, 5, 0).normalize(5), 0.0);
+    }
+
+    #[test]
+    fn parse_devd_create_is_an_add() {
+        match parse_devd_line("!system=DEVFS subsystem=CDEV type=CREATE cdev=input/event5") {
+            Some(DeviceNotification::Added { sysname, devnode }) => {
+                assert_eq!(sysname, CString::new("event5").unwrap());
+                assert_eq!(devnode, CString::new("/dev/input/event5").unwrap());
+            },
+            _ => panic!("expected Added"),
+        }
+    }
+
+    #[test]
+    fn parse_devd_destroy_is_a_remove() {
+        match parse_devd_line("!system=DEVFS subsystem=CDEV type=DESTROY cdev=input/event5") {
+            Some(DeviceNotification::Removed { sysname }) => {
+                assert_eq!(sysname, CString::new("event5").unwrap());
+            },
+            _ => panic!("expected Removed"),
+        }
+    }
+
+    #[test]
+    fn parse_devd_ignores_non_input_cdev() {
+        assert!(parse_devd_line("!system=DEVFS subsystem=CDEV type=CREATE cdev=ttyv0").is_none());
+    }
+
+    #[test]
+    fn parse_devd_ignores_other_subsystems() {
+        assert!(parse_devd_line("!system=USB subsystem=DEVICE type=ATTACH cdev=input/event5").is_none());
+    }
+
+    #[test]
+    fn parse_devd_ignores_unmarked_and_incomplete_lines() {
+        assert!(parse_devd_line("system=DEVFS subsystem=CDEV type=CREATE cdev=input/event5").is_none());
+        assert!(parse_devd_line("!system=DEVFS type=CREATE cdev=input/event5").is_none());
+        assert!(parse_devd_line("!system=DEVFS subsystem=CDEV cdev=input/event5").is_none());
+    }
+}